@@ -2,13 +2,17 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use csv::Writer;
 use env_logger::Builder;
 use log::{info, warn};
+use memmap2::Mmap;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt::Debug;
 use std::fs::File;
+use std::io::{BufWriter, Write as IoWrite};
+use std::marker::PhantomData;
 use std::sync::atomic::AtomicBool;
 
 use exrs::binance_f::api::*;
@@ -45,6 +49,8 @@ pub struct Data {
     pub influx_database: bool,
     pub file_format: String,
     pub file_url: String,
+    #[serde(default)]
+    pub combined: bool,
 }
 
 type Record<'a> = (
@@ -66,6 +72,13 @@ type Record<'a> = (
 //     pub bids_qty: Vec<Decimal>,
 // }
 
+/// Outcome of `Orderbook::verify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyResult {
+    Ok,
+    SequenceGap,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Orderbook {
     pub symbol: String,
@@ -142,7 +155,14 @@ impl Orderbook {
         }
     }
 
-    pub fn verify(&mut self, pu_id: u64, check_bid_ask_overlapping: bool) -> bool {
+    /// Checks `pu_id` against `final_update_id`, returning `SequenceGap` so the
+    /// caller can resnapshot instead of silently continuing from a diverged book.
+    ///
+    /// Binance's futures depth-diff stream carries no checksum field, so there
+    /// is nothing to compare a computed checksum against here. When
+    /// `check_bid_ask_overlapping` is set, a crossed book (best bid >= best ask)
+    /// is also reported as `SequenceGap`.
+    pub fn verify(&mut self, pu_id: u64, check_bid_ask_overlapping: bool) -> VerifyResult {
         if check_bid_ask_overlapping {
             if self.bids.len() > 0 && self.asks.len() > 0 {
                 if self.best_bid().unwrap().0 >= self.best_ask().unwrap().0 {
@@ -151,12 +171,16 @@ impl Orderbook {
                         self.best_bid().unwrap().0,
                         self.best_ask().unwrap().0
                     );
-                    return false;
+                    return VerifyResult::SequenceGap;
                 }
             }
         }
 
-        self.final_update_id == pu_id
+        if self.final_update_id != pu_id {
+            return VerifyResult::SequenceGap;
+        }
+
+        VerifyResult::Ok
     }
 
     /// Returns the price of the best bid
@@ -198,38 +222,923 @@ impl Orderbook {
     }
 }
 
-struct WebSocketHandler {
+/// A destination for recorded depth/trade/candle frames. `WebSocketHandler` is
+/// written against this trait rather than a concrete writer so the output
+/// format can be picked at runtime from `Config.data.file_format`.
+pub trait RecordSink: Send {
+    fn write_depth(&mut self, event: &Record) -> Result<(), Box<dyn Error>>;
+    fn write_trade(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>>;
+    fn write_candle(&mut self, event: &Candle) -> Result<(), Box<dyn Error>>;
+    fn flush(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+/// Plain-text sink, one CSV record per row.
+struct CsvSink {
     wrt: Writer<File>,
 }
 
-impl WebSocketHandler {
-    pub fn new(local_wrt: Writer<File>) -> Self {
-        WebSocketHandler { wrt: local_wrt }
+impl CsvSink {
+    pub fn new(wrt: Writer<File>) -> Self {
+        CsvSink { wrt }
     }
+}
 
-    // serialize Depth as CSV records
-    pub fn write_depth_to_file(&mut self, event: &Record) -> Result<(), Box<dyn Error>> {
+impl RecordSink for CsvSink {
+    fn write_depth(&mut self, event: &Record) -> Result<(), Box<dyn Error>> {
         self.wrt.serialize(event)?;
+        Ok(())
+    }
 
+    fn write_trade(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>> {
+        self.wrt.serialize(event)?;
         Ok(())
     }
 
-    // serialize Trades as CSV records
-    pub fn write_trades_to_file(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>> {
+    fn write_candle(&mut self, event: &Candle) -> Result<(), Box<dyn Error>> {
         self.wrt.serialize(event)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.wrt.flush()?;
+        Ok(())
+    }
+}
+
+/// Encodes a single frame's payload bytes for a length-framed binary sink.
+/// `BincodeCodec` and `PostcardCodec` are the two formats `Config.data.file_format`
+/// can select.
+trait FrameCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>>;
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>>;
+}
+
+struct BincodeCodec;
+
+impl FrameCodec for BincodeCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+struct PostcardCodec;
+
+impl FrameCodec for PostcardCodec {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// Compact binary sink: each record is written as a little-endian `u32` length
+/// prefix followed by its `FrameCodec`-encoded bytes.
+struct BinarySink<C> {
+    wrt: BufWriter<File>,
+    _codec: PhantomData<C>,
+}
+
+impl<C: FrameCodec> BinarySink<C> {
+    pub fn new(file: File) -> Self {
+        BinarySink {
+            wrt: BufWriter::new(file),
+            _codec: PhantomData,
+        }
+    }
+
+    fn write_frame<T: Serialize>(&mut self, value: &T) -> Result<(), Box<dyn Error>> {
+        let bytes = C::encode(value)?;
+        self.wrt.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.wrt.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+impl<C: FrameCodec + Send> RecordSink for BinarySink<C> {
+    fn write_depth(&mut self, event: &Record) -> Result<(), Box<dyn Error>> {
+        self.write_frame(event)
+    }
+
+    fn write_trade(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>> {
+        self.write_frame(event)
+    }
+
+    fn write_candle(&mut self, event: &Candle) -> Result<(), Box<dyn Error>> {
+        self.write_frame(event)
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.wrt.flush()?;
+        Ok(())
+    }
+}
+
+/// Streams recorded frames to an InfluxDB line-protocol HTTP endpoint instead of
+/// a rotating file, selected by `Config.data.influx_database`. Encoding happens
+/// inline in `write_*`; the actual POSTs are done by a background task so the
+/// synchronous `RecordSink` methods never block on the network.
+struct InfluxSink {
+    lines: tokio::sync::mpsc::UnboundedSender<String>,
+}
+
+const INFLUX_BATCH_SIZE: usize = 500;
+const INFLUX_MAX_ATTEMPTS: u32 = 5;
+
+impl InfluxSink {
+    pub fn new(file_url: String) -> Self {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        actix_rt::spawn(influx_writer(file_url, rx));
+        InfluxSink { lines: tx }
+    }
+
+    fn send_line(&mut self, line: String) -> Result<(), Box<dyn Error>> {
+        self.lines
+            .send(line)
+            .map_err(|e| Box::new(e) as Box<dyn Error>)
+    }
+}
+
+async fn influx_writer(
+    file_url: String,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<String>,
+) {
+    let client = reqwest::Client::new();
+    let mut batch = Vec::with_capacity(INFLUX_BATCH_SIZE);
+
+    while let Some(line) = rx.recv().await {
+        batch.push(line);
+        if batch.len() >= INFLUX_BATCH_SIZE {
+            post_batch(&client, &file_url, &mut batch).await;
+        }
+    }
+
+    if !batch.is_empty() {
+        post_batch(&client, &file_url, &mut batch).await;
+    }
+}
+
+async fn post_batch(client: &reqwest::Client, file_url: &str, batch: &mut Vec<String>) {
+    let body = batch.join("\n");
+    let mut backoff = std::time::Duration::from_millis(200);
+
+    for attempt in 1..=INFLUX_MAX_ATTEMPTS {
+        match client.post(file_url).body(body.clone()).send().await {
+            Ok(response) if response.status().is_success() => break,
+            Ok(response) => warn!("influx write rejected ({}): {}", attempt, response.status()),
+            Err(error) => warn!("influx write error ({}): {}", attempt, error),
+        }
+
+        if attempt == INFLUX_MAX_ATTEMPTS {
+            warn!("influx write failed after {} attempts, dropping batch", attempt);
+            break;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+
+    batch.clear();
+}
+
+impl RecordSink for InfluxSink {
+    fn write_depth(&mut self, event: &Record) -> Result<(), Box<dyn Error>> {
+        let (symbol, timestamp, asks_price, bids_price, asks_qty, bids_qty) = event;
+        let timestamp_ns = (**timestamp as u128) * 1_000_000;
+
+        let mut fields = Vec::new();
+        if let (Some(bid), Some(ask)) = (bids_price.first(), asks_price.first()) {
+            fields.push(format!("best_bid={}", bid));
+            fields.push(format!("best_ask={}", ask));
+        }
+        for (i, price) in bids_price.iter().take(20).enumerate() {
+            fields.push(format!("bid_price{}={}", i, price));
+        }
+        for (i, qty) in bids_qty.iter().take(20).enumerate() {
+            fields.push(format!("bid_qty{}={}", i, qty));
+        }
+        for (i, price) in asks_price.iter().take(20).enumerate() {
+            fields.push(format!("ask_price{}={}", i, price));
+        }
+        for (i, qty) in asks_qty.iter().take(20).enumerate() {
+            fields.push(format!("ask_qty{}={}", i, qty));
+        }
+
+        self.send_line(format!(
+            "depth,symbol={} {} {}",
+            symbol,
+            fields.join(","),
+            timestamp_ns
+        ))
+    }
+
+    fn write_trade(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>> {
+        let side = if event.is_buyer_maker { "sell" } else { "buy" };
+        let timestamp_ns = (event.event_time as u128) * 1_000_000;
+
+        self.send_line(format!(
+            "trade,symbol={},side={} price={},qty={} {}",
+            event.symbol, side, event.price, event.qty, timestamp_ns
+        ))
+    }
+
+    fn write_candle(&mut self, candle: &Candle) -> Result<(), Box<dyn Error>> {
+        let timestamp_ns = (candle.open_time as u128) * 1_000_000;
+
+        self.send_line(format!(
+            "candle,symbol={},interval={} open={},high={},low={},close={},base_volume={},quote_volume={},trade_count={}i {}",
+            candle.symbol,
+            candle.interval,
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.base_volume,
+            candle.quote_volume,
+            candle.trade_count,
+            timestamp_ns
+        ))
+    }
 
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
         Ok(())
     }
 }
 
-async fn run_depth(symbol: String) {
+/// Builds the sink for a recording task, following `Config.data`: an `InfluxSink`
+/// writing to `data.file_url` when `influx_database` is set, otherwise a file sink
+/// named by `data.file_format` ("csv" | "bincode" | "postcard") at `{base_path}.{ext}`.
+fn build_sink(data: &Data, base_path: &str) -> Box<dyn RecordSink> {
+    if data.influx_database {
+        return Box::new(InfluxSink::new(data.file_url.clone()));
+    }
+
+    match data.file_format.as_str() {
+        "bincode" => {
+            let file = File::create(format!("{}.bin", base_path)).unwrap();
+            Box::new(BinarySink::<BincodeCodec>::new(file))
+        }
+        "postcard" => {
+            let file = File::create(format!("{}.pc", base_path)).unwrap();
+            Box::new(BinarySink::<PostcardCodec>::new(file))
+        }
+        _ => {
+            let wrt = csv::Writer::from_path(format!("{}.csv", base_path)).unwrap();
+            Box::new(CsvSink::new(wrt))
+        }
+    }
+}
+
+/// Reads back the length-framed records written by a `BinarySink<C>`, mapping
+/// the file into memory and deserializing one frame per length prefix. Not
+/// wired into `run_depth`/`run_trades` — those only ever write; this is the
+/// read-side counterpart for offline tooling (backtests, analysis scripts)
+/// that needs to load a captured `.bin` (bincode) or `.pc` (postcard) file back in.
+struct FrameReader<C> {
+    mmap: Mmap,
+    offset: usize,
+    _codec: PhantomData<C>,
+}
+
+impl<C: FrameCodec> FrameReader<C> {
+    pub fn open(path: &std::path::Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(FrameReader {
+            mmap,
+            offset: 0,
+            _codec: PhantomData,
+        })
+    }
+
+    /// Returns the next frame, or `None` once the mapped file is exhausted.
+    pub fn next_frame<T: DeserializeOwned>(&mut self) -> Result<Option<T>, Box<dyn Error>> {
+        if self.offset + 4 > self.mmap.len() {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes(self.mmap[self.offset..self.offset + 4].try_into().unwrap());
+        self.offset += 4;
+
+        let start = self.offset;
+        let end = start + len as usize;
+        if end > self.mmap.len() {
+            return Err(format!(
+                "truncated frame: length prefix {} at offset {} exceeds remaining {} bytes",
+                len,
+                start,
+                self.mmap.len() - start
+            )
+            .into());
+        }
+        self.offset = end;
+
+        Ok(Some(C::decode(&self.mmap[start..end])?))
+    }
+}
+
+#[cfg(test)]
+mod frame_reader_tests {
+    use super::*;
+    use std::io::Write;
+
+    fn sample_candle(close: Decimal) -> Candle {
+        Candle {
+            symbol: "ethusdt".to_string(),
+            interval: "1m".to_string(),
+            open_time: 0,
+            close_time: 59_999,
+            open: dec!(100),
+            high: dec!(105),
+            low: dec!(99),
+            close,
+            base_volume: dec!(10),
+            quote_volume: dec!(1000),
+            trade_count: 4,
+            taker_buy_base_volume: dec!(5),
+            taker_buy_quote_volume: dec!(500),
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("exrs_frame_reader_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn round_trips_frames_written_by_binary_sink() {
+        let path = temp_path("round_trip.bincode");
+        {
+            let file = File::create(&path).unwrap();
+            let mut sink: BinarySink<BincodeCodec> = BinarySink::new(file);
+            sink.write_candle(&sample_candle(dec!(101))).unwrap();
+            sink.write_candle(&sample_candle(dec!(102))).unwrap();
+            sink.flush().unwrap();
+        }
+
+        let mut reader: FrameReader<BincodeCodec> = FrameReader::open(&path).unwrap();
+        let first: Candle = reader.next_frame().unwrap().unwrap();
+        let second: Candle = reader.next_frame().unwrap().unwrap();
+        let third: Option<Candle> = reader.next_frame().unwrap();
+
+        assert_eq!(first.close, dec!(101));
+        assert_eq!(second.close, dec!(102));
+        assert!(third.is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn round_trips_frames_with_postcard_codec() {
+        let path = temp_path("round_trip.postcard");
+        {
+            let file = File::create(&path).unwrap();
+            let mut sink: BinarySink<PostcardCodec> = BinarySink::new(file);
+            sink.write_candle(&sample_candle(dec!(50))).unwrap();
+            sink.flush().unwrap();
+        }
+
+        let mut reader: FrameReader<PostcardCodec> = FrameReader::open(&path).unwrap();
+        let frame: Candle = reader.next_frame().unwrap().unwrap();
+        assert_eq!(frame.close, dec!(50));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn errors_on_a_truncated_trailing_frame_instead_of_panicking() {
+        let path = temp_path("truncated.bincode");
+        {
+            let mut file = File::create(&path).unwrap();
+            // length prefix claims 100 bytes follow, but none do.
+            file.write_all(&100u32.to_le_bytes()).unwrap();
+        }
+
+        let mut reader: FrameReader<BincodeCodec> = FrameReader::open(&path).unwrap();
+        let result: Result<Option<Candle>, Box<dyn Error>> = reader.next_frame();
+        assert!(result.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+struct WebSocketHandler {
+    sink: Box<dyn RecordSink>,
+}
+
+impl WebSocketHandler {
+    pub fn new(sink: Box<dyn RecordSink>) -> Self {
+        WebSocketHandler { sink }
+    }
+
+    // serialize Depth records through the configured sink
+    pub fn write_depth_to_file(&mut self, event: &Record) -> Result<(), Box<dyn Error>> {
+        self.sink.write_depth(event)
+    }
+
+    // serialize Trades records through the configured sink
+    pub fn write_trades_to_file(&mut self, event: &AggrTradesEvent) -> Result<(), Box<dyn Error>> {
+        self.sink.write_trade(event)
+    }
+
+    // serialize Candle records through the configured sink
+    pub fn write_candle_to_file(&mut self, event: &Candle) -> Result<(), Box<dyn Error>> {
+        self.sink.write_candle(event)
+    }
+
+    // flush the configured sink's buffered writes; called at file-rotation
+    // boundaries so a day's records reach disk immediately rather than
+    // relying solely on the old handler's `Drop` impl to flush them.
+    pub fn flush_to_file(&mut self) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()
+    }
+}
+
+/// A single finished or in-progress OHLCV candle for one symbol/interval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candle {
+    pub symbol: String,
+    pub interval: String,
+    pub open_time: u64,
+    pub close_time: u64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub base_volume: Decimal,
+    pub quote_volume: Decimal,
+    pub trade_count: u64,
+    pub taker_buy_base_volume: Decimal,
+    pub taker_buy_quote_volume: Decimal,
+}
+
+/// Folds a stream of `AggrTradesEvent`s into rolling OHLCV candles at a fixed
+/// resolution, mirroring the role `Orderbook` plays for depth updates.
+pub struct CandleBuilder {
+    symbol: String,
+    interval: String,
+    interval_ms: u64,
+    current: Option<Candle>,
+}
+
+impl CandleBuilder {
+    pub fn new(symbol: String, interval: String, interval_ms: u64) -> Self {
+        CandleBuilder {
+            symbol,
+            interval,
+            interval_ms,
+            current: None,
+        }
+    }
+
+    fn new_candle(&self, trade: &AggrTradesEvent, bucket: u64) -> Candle {
+        let open_time = bucket * self.interval_ms;
+        let taker_buy = !trade.is_buyer_maker;
+
+        Candle {
+            symbol: self.symbol.clone(),
+            interval: self.interval.clone(),
+            open_time,
+            close_time: open_time + self.interval_ms - 1,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            base_volume: trade.qty,
+            quote_volume: trade.price * trade.qty,
+            trade_count: 1,
+            taker_buy_base_volume: if taker_buy { trade.qty } else { dec!(0) },
+            taker_buy_quote_volume: if taker_buy {
+                trade.price * trade.qty
+            } else {
+                dec!(0)
+            },
+        }
+    }
+
+    /// Folds `trade` into the open candle, returning the previous candle once
+    /// `trade` rolls into a new bucket so the caller can flush it to a sink.
+    pub fn on_trade(&mut self, trade: &AggrTradesEvent) -> Option<Candle> {
+        let bucket = trade.event_time / self.interval_ms;
+        let interval_ms = self.interval_ms;
+        let taker_buy = !trade.is_buyer_maker;
+
+        if let Some(candle) = self.current.as_mut() {
+            if candle.open_time / interval_ms == bucket {
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.close = trade.price;
+                candle.base_volume += trade.qty;
+                candle.quote_volume += trade.price * trade.qty;
+                candle.trade_count += 1;
+                if taker_buy {
+                    candle.taker_buy_base_volume += trade.qty;
+                    candle.taker_buy_quote_volume += trade.price * trade.qty;
+                }
+                return None;
+            }
+        }
+
+        let finished = self.current.take();
+        self.current = Some(self.new_candle(trade, bucket));
+        finished
+    }
+}
+
+#[cfg(test)]
+mod candle_builder_tests {
+    use super::*;
+
+    fn trade(event_time: u64, price: Decimal, qty: Decimal, is_buyer_maker: bool) -> AggrTradesEvent {
+        AggrTradesEvent {
+            event_type: "aggTrade".to_string(),
+            event_time,
+            symbol: "ethusdt".to_string(),
+            aggregated_trade_id: 1,
+            price,
+            qty,
+            first_break_trade_id: 1,
+            last_break_trade_id: 1,
+            trade_order_time: event_time,
+            is_buyer_maker,
+        }
+    }
+
+    #[test]
+    fn first_trade_opens_a_candle_and_returns_none() {
+        let mut builder = CandleBuilder::new("ethusdt".to_string(), "1m".to_string(), 60_000);
+        let finished = builder.on_trade(&trade(0, dec!(100), dec!(1), false));
+        assert!(finished.is_none());
+    }
+
+    #[test]
+    fn trades_in_the_same_bucket_fold_into_the_open_candle() {
+        let mut builder = CandleBuilder::new("ethusdt".to_string(), "1m".to_string(), 60_000);
+        builder.on_trade(&trade(0, dec!(100), dec!(1), false));
+        let finished = builder.on_trade(&trade(30_000, dec!(105), dec!(2), true));
+
+        assert!(finished.is_none());
+        let candle = builder.current.as_ref().unwrap();
+        assert_eq!(candle.open, dec!(100));
+        assert_eq!(candle.high, dec!(105));
+        assert_eq!(candle.low, dec!(100));
+        assert_eq!(candle.close, dec!(105));
+        assert_eq!(candle.base_volume, dec!(3));
+        assert_eq!(candle.trade_count, 2);
+    }
+
+    #[test]
+    fn taker_buy_volume_only_counts_buyer_taker_trades() {
+        let mut builder = CandleBuilder::new("ethusdt".to_string(), "1m".to_string(), 60_000);
+        // is_buyer_maker: false means the buyer was the taker.
+        builder.on_trade(&trade(0, dec!(100), dec!(1), false));
+        builder.on_trade(&trade(1_000, dec!(100), dec!(4), true));
+
+        let candle = builder.current.as_ref().unwrap();
+        assert_eq!(candle.base_volume, dec!(5));
+        assert_eq!(candle.taker_buy_base_volume, dec!(1));
+    }
+
+    #[test]
+    fn a_trade_in_a_new_bucket_rolls_over_and_returns_the_finished_candle() {
+        let mut builder = CandleBuilder::new("ethusdt".to_string(), "1m".to_string(), 60_000);
+        builder.on_trade(&trade(0, dec!(100), dec!(1), false));
+        builder.on_trade(&trade(30_000, dec!(105), dec!(1), false));
+
+        let finished = builder.on_trade(&trade(60_000, dec!(110), dec!(1), false));
+
+        let finished = finished.expect("bucket rollover should flush the previous candle");
+        assert_eq!(finished.open_time, 0);
+        assert_eq!(finished.close, dec!(105));
+        assert_eq!(finished.trade_count, 2);
+
+        let current = builder.current.as_ref().unwrap();
+        assert_eq!(current.open_time, 60_000);
+        assert_eq!(current.open, dec!(110));
+    }
+}
+
+/// Parses a `Config.data.channels` entry like `"candle@1m"` into its display
+/// label (`"1m"`) and resolution in milliseconds.
+fn parse_candle_channel(channel: &str) -> Option<(String, u64)> {
+    let label = channel.strip_prefix("candle@")?;
+    let split_at = label.len().checked_sub(1)?;
+    let (value, unit) = label.split_at(split_at);
+    let value: u64 = value.parse().ok()?;
+    let interval_ms = match unit {
+        "s" => value * 1_000,
+        "m" => value * 60_000,
+        "h" => value * 3_600_000,
+        _ => return None,
+    };
+
+    Some((label.to_string(), interval_ms))
+}
+
+#[cfg(test)]
+mod parse_candle_channel_tests {
+    use super::*;
+
+    #[test]
+    fn parses_seconds_minutes_and_hours() {
+        assert_eq!(parse_candle_channel("candle@30s"), Some(("30s".to_string(), 30_000)));
+        assert_eq!(parse_candle_channel("candle@1m"), Some(("1m".to_string(), 60_000)));
+        assert_eq!(parse_candle_channel("candle@4h"), Some(("4h".to_string(), 14_400_000)));
+    }
+
+    #[test]
+    fn rejects_non_candle_channels_and_unknown_units() {
+        assert_eq!(parse_candle_channel("aggTrade"), None);
+        assert_eq!(parse_candle_channel("candle@1d"), None);
+        assert_eq!(parse_candle_channel("candle@"), None);
+    }
+}
+
+/// Binance's combined-stream envelope: `{"stream": "<symbol>@<channel>", "data": <payload>}`.
+/// `data` is kept as a raw `Value` rather than an untagged enum over
+/// `DepthOrderBookEvent`/`AggrTradesEvent`: untagged matching tries one variant
+/// then the other, so a payload that happens to structurally fit the wrong
+/// variant would silently misroute. The `stream` suffix names the channel
+/// unambiguously, so routing parses that instead and only then decodes `data`
+/// into the matching event type.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamEnvelope {
+    pub stream: String,
+    pub data: serde_json::Value,
+}
+
+/// Builds the combined-stream subscription list from `symbol x channels`, e.g.
+/// `["ethusdt@depth@100ms", "ethusdt@aggTrade"]`. `"candle@..."` entries are
+/// dropped here since candles are folded out of the `aggTrade` stream. Symbols
+/// are lowercased since Binance always echoes the `stream` name lowercase, and
+/// dispatch looks `symbols` up by that same lowercased key.
+fn build_stream_list(data: &Data) -> Vec<String> {
+    let mut streams = Vec::new();
+    for symbol in &data.symbol {
+        let symbol = symbol.to_lowercase();
+        for ch in &data.channels {
+            match ch.as_str() {
+                "depth@100ms" => streams.push(format!("{}@depth@100ms", symbol)),
+                "aggTrade" => streams.push(format!("{}@aggTrade", symbol)),
+                ch if ch.starts_with("candle@") => {}
+                _ => warn!("Error: channel type not support!"),
+            }
+        }
+    }
+    streams
+}
+
+/// Per-symbol recording state a combined connection dispatches envelopes into.
+/// `depth`/`trades` are `None` when the symbol wasn't subscribed to that channel.
+struct SymbolState {
+    depth: Option<DepthState>,
+    trades: Option<TradesState>,
+}
+
+struct DepthState {
+    orderbook: Orderbook,
+    snapshot_last_update_id: u64,
+    handler: WebSocketHandler,
+    tmr_dt: DateTime<Utc>,
+}
+
+struct TradesState {
+    handler: WebSocketHandler,
+    tmr_dt: DateTime<Utc>,
+    candle_builders: Vec<CandleBuilder>,
+    candle_handlers: Vec<WebSocketHandler>,
+}
+
+async fn init_symbol_state(
+    symbol: &str,
+    data: &Data,
+    candle_resolutions: &[(String, u64)],
+    market: &FuturesMarket,
+) -> SymbolState {
+    let today = Utc::today();
+    let tmr_dt = today.and_hms(23, 59, 59);
+
+    let depth = if data.channels.iter().any(|ch| ch == "depth@100ms") {
+        let partial_init: OrderBookPartial = market.get_custom_depth(symbol.to_string(), 1000).await.unwrap();
+        let mut orderbook = Orderbook::new(symbol.to_string());
+        orderbook.partial(&partial_init);
+
+        let base_path = format!("{}-{}-{:?}", symbol, "depth20", today);
+        Some(DepthState {
+            orderbook,
+            snapshot_last_update_id: partial_init.last_update_id,
+            handler: WebSocketHandler::new(build_sink(data, &base_path)),
+            tmr_dt,
+        })
+    } else {
+        None
+    };
+
+    let trades = if data.channels.iter().any(|ch| ch == "aggTrade") {
+        let base_path = format!("{}-{}-{:?}", symbol, "trades", today);
+        let candle_builders = candle_resolutions
+            .iter()
+            .map(|(label, interval_ms)| CandleBuilder::new(symbol.to_string(), label.clone(), *interval_ms))
+            .collect();
+        let candle_handlers = candle_resolutions
+            .iter()
+            .map(|(label, _)| {
+                let base_path = format!("{}-candle{}-{:?}", symbol, label, today);
+                WebSocketHandler::new(build_sink(data, &base_path))
+            })
+            .collect();
+
+        Some(TradesState {
+            handler: WebSocketHandler::new(build_sink(data, &base_path)),
+            tmr_dt,
+            candle_builders,
+            candle_handlers,
+        })
+    } else {
+        None
+    };
+
+    SymbolState { depth, trades }
+}
+
+async fn handle_depth_envelope(
+    symbol: &str,
+    data: &Data,
+    market: &FuturesMarket,
+    state: &mut DepthState,
+    msg: DepthOrderBookEvent,
+) {
+    if msg.final_update_id < state.snapshot_last_update_id {
+        return;
+    } else if msg.first_update_id <= state.snapshot_last_update_id
+        && msg.final_update_id >= state.snapshot_last_update_id
+    {
+        state.orderbook.update(&msg)
+    } else {
+        match state.orderbook.verify(msg.previous_final_update_id, false) {
+            VerifyResult::Ok => {
+                info!("verfiy passed");
+                state.orderbook.update(&msg)
+            }
+            VerifyResult::SequenceGap => {
+                warn!("verfiy failed");
+                let partial_init: OrderBookPartial =
+                    market.get_custom_depth(symbol.to_string(), 1000).await.unwrap();
+                state.orderbook.partial(&partial_init);
+            }
+        }
+    }
+
+    let event = state.orderbook.get_depth(20).unwrap();
+
+    if DateTime::<Utc>::from_utc(
+        NaiveDateTime::from_timestamp((msg.event_time / 1000) as i64, 0),
+        Utc,
+    ) > state.tmr_dt
+    {
+        state.tmr_dt = Utc::today().and_hms(23, 59, 59);
+        if let Err(error) = state.handler.flush_to_file() {
+            warn!("{}", error);
+        };
+        let base_path = format!("{}-{}-{:?}", symbol, "depth20", Utc::today());
+        state.handler = WebSocketHandler::new(build_sink(data, &base_path));
+    }
+
+    if let Err(error) = state.handler.write_depth_to_file(&event) {
+        warn!("{}", error);
+    };
+}
+
+fn handle_trade_envelope(symbol: &str, data: &Data, state: &mut TradesState, event: AggrTradesEvent) {
+    let rolled_over = DateTime::<Utc>::from_utc(
+        NaiveDateTime::from_timestamp((event.event_time / 1000) as i64, 0),
+        Utc,
+    ) > state.tmr_dt;
+
+    if rolled_over {
+        state.tmr_dt = Utc::today().and_hms(23, 59, 59);
+        if let Err(error) = state.handler.flush_to_file() {
+            warn!("{}", error);
+        };
+        let base_path = format!("{}-{}-{:?}", symbol, "trades", Utc::today());
+        state.handler = WebSocketHandler::new(build_sink(data, &base_path));
+    }
+
+    if let Err(error) = state.handler.write_trades_to_file(&event) {
+        warn!("{}", error);
+    };
+
+    for (builder, handler) in state
+        .candle_builders
+        .iter_mut()
+        .zip(state.candle_handlers.iter_mut())
+    {
+        if rolled_over {
+            if let Err(error) = handler.flush_to_file() {
+                warn!("{}", error);
+            };
+            let base_path = format!("{}-candle{}-{:?}", symbol, builder.interval, Utc::today());
+            *handler = WebSocketHandler::new(build_sink(data, &base_path));
+        }
+
+        if let Some(candle) = builder.on_trade(&event) {
+            if let Err(error) = handler.write_candle_to_file(&candle) {
+                warn!("{}", error);
+            };
+        }
+    }
+}
+
+/// Subscribes to every `(symbol, channel)` pair on a single combined websocket
+/// instead of one connection per pair, and routes each incoming envelope to the
+/// matching symbol's depth/trade state by its `stream` name.
+async fn run_combined(data: Data) {
+    let streams = build_stream_list(&data);
+
+    let api_key_user = Some("YOUR_KEY".into());
+    let market: FuturesMarket = BinanceF::new(api_key_user, None);
+
+    let candle_resolutions: Vec<(String, u64)> = data
+        .channels
+        .iter()
+        .filter_map(|ch| parse_candle_channel(ch))
+        .collect();
+
+    let mut symbols: HashMap<String, SymbolState> = HashMap::new();
+    for symbol in &data.symbol {
+        let state = init_symbol_state(symbol, &data, &candle_resolutions, &market).await;
+        symbols.insert(symbol.to_lowercase(), state);
+    }
+
+    let keep_running = AtomicBool::new(true);
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1000);
+    let mut web_socket: FuturesWebSockets<StreamEnvelope> = FuturesWebSockets::new(tx);
+
+    web_socket.connect_multiple_streams(&streams).await.unwrap();
+
+    actix_rt::spawn(async move {
+        loop {
+            let envelope = rx.recv().await.unwrap();
+
+            // Binance echoes the `stream` name lowercase, so the symbol is
+            // always lowercase here regardless of the config's casing.
+            let symbol = match envelope.stream.split('@').next() {
+                Some(symbol) => symbol.to_string(),
+                None => {
+                    warn!("combined stream event with malformed stream name: {}", envelope.stream);
+                    continue;
+                }
+            };
+
+            let state = match symbols.get_mut(&symbol) {
+                Some(state) => state,
+                None => {
+                    warn!("combined stream event for unknown symbol: {}", envelope.stream);
+                    continue;
+                }
+            };
+
+            if envelope.stream.contains("@depth") {
+                match serde_json::from_value::<DepthOrderBookEvent>(envelope.data) {
+                    Ok(event) => {
+                        if let Some(depth) = state.depth.as_mut() {
+                            handle_depth_envelope(&symbol, &data, &market, depth, event).await;
+                        }
+                    }
+                    Err(e) => warn!("failed to decode depth payload for {}: {}", envelope.stream, e),
+                }
+            } else if envelope.stream.contains("@aggTrade") {
+                match serde_json::from_value::<AggrTradesEvent>(envelope.data) {
+                    Ok(event) => {
+                        if let Some(trades) = state.trades.as_mut() {
+                            handle_trade_envelope(&symbol, &data, trades, event);
+                        }
+                    }
+                    Err(e) => warn!("failed to decode trade payload for {}: {}", envelope.stream, e),
+                }
+            } else {
+                warn!("combined stream event on unrecognized stream: {}", envelope.stream);
+            }
+        }
+    });
+
+    while let Err(e) = web_socket.event_loop(&keep_running).await {
+        warn!("combined web_socket event_loop Error: {}, starting reconnect...", e);
+
+        while let Err(e) = web_socket.connect_multiple_streams(&streams).await {
+            warn!("combined web_socket connect Error: {}, try again...", e);
+        }
+    }
+}
+
+async fn run_depth(symbol: String, data: Data) {
     let mut tmr_dt = Utc::today().and_hms(23, 59, 59);
-    
-    let file_name = format!("{}-{}-{:?}.csv", symbol, "depth20", Utc::today());
-    let file_path = std::path::Path::new(&file_name);
-    let local_wrt = csv::Writer::from_path(file_path).unwrap();
-    let mut web_socket_handler = WebSocketHandler::new(local_wrt);
-    
+
+    let base_path = format!("{}-{}-{:?}", symbol, "depth20", Utc::today());
+    let mut web_socket_handler = WebSocketHandler::new(build_sink(&data, &base_path));
+
     let api_key_user = Some("YOUR_KEY".into());
     let market: FuturesMarket = BinanceF::new(api_key_user, None);
     
@@ -255,14 +1164,19 @@ async fn run_depth(symbol: String) {
                 && msg.final_update_id >= partial_init.last_update_id
             {
                 orderbook.update(&msg)
-            } else if orderbook.verify(msg.previous_final_update_id, false) {
-                info!("verfiy passed");
-                orderbook.update(&msg)
             } else {
-                warn!("verfiy failed");
-                let partial_init: OrderBookPartial =
-                    market.get_custom_depth(symbol.clone(), 1000).await.unwrap();
-                orderbook.partial(&partial_init);
+                match orderbook.verify(msg.previous_final_update_id, false) {
+                    VerifyResult::Ok => {
+                        info!("verfiy passed");
+                        orderbook.update(&msg)
+                    }
+                    VerifyResult::SequenceGap => {
+                        warn!("verfiy failed");
+                        let partial_init: OrderBookPartial =
+                            market.get_custom_depth(symbol.clone(), 1000).await.unwrap();
+                        orderbook.partial(&partial_init);
+                    }
+                }
             }
     
             let event = orderbook.get_depth(20).unwrap();
@@ -273,12 +1187,13 @@ async fn run_depth(symbol: String) {
             ) > tmr_dt
             {
                 tmr_dt = Utc::today().and_hms(23, 59, 59);
-                let file_name = format!("{}-{}-{:?}.csv", symbol, "depth20", Utc::today());
-                let file_path = std::path::Path::new(&file_name);
-                let local_wrt = csv::Writer::from_path(file_path).unwrap();
-                web_socket_handler = WebSocketHandler::new(local_wrt);
+                if let Err(error) = web_socket_handler.flush_to_file() {
+                    warn!("{}", error);
+                };
+                let base_path = format!("{}-{}-{:?}", symbol, "depth20", Utc::today());
+                web_socket_handler = WebSocketHandler::new(build_sink(&data, &base_path));
             }
-    
+
             if let Err(error) = web_socket_handler.write_depth_to_file(&event) {
                 warn!("{}", error);
             };
@@ -294,14 +1209,24 @@ async fn run_depth(symbol: String) {
     }
 }
 
-async fn run_trades(symbol: String) {
+async fn run_trades(symbol: String, candle_resolutions: Vec<(String, u64)>, data: Data) {
     let mut tmr_dt = Utc::today().and_hms(23, 59, 59);
-    
-    let file_name = format!("{}-{}-{:?}.csv", symbol, "trades", Utc::today());
-    let file_path = std::path::Path::new(&file_name);
-    let local_wrt = csv::Writer::from_path(file_path).unwrap();
-    let mut web_socket_handler = WebSocketHandler::new(local_wrt);
-    
+
+    let base_path = format!("{}-{}-{:?}", symbol, "trades", Utc::today());
+    let mut web_socket_handler = WebSocketHandler::new(build_sink(&data, &base_path));
+
+    let mut candle_builders: Vec<CandleBuilder> = candle_resolutions
+        .iter()
+        .map(|(label, interval_ms)| CandleBuilder::new(symbol.clone(), label.clone(), *interval_ms))
+        .collect();
+    let mut candle_handlers: Vec<WebSocketHandler> = candle_resolutions
+        .iter()
+        .map(|(label, _)| {
+            let base_path = format!("{}-candle{}-{:?}", symbol, label, Utc::today());
+            WebSocketHandler::new(build_sink(&data, &base_path))
+        })
+        .collect();
+
     let api_key_user = Some("YOUR_KEY".into());
     let market: FuturesMarket = BinanceF::new(api_key_user, None);
     
@@ -317,24 +1242,42 @@ async fn run_trades(symbol: String) {
         loop {
             let event = rx.recv().await.unwrap();
 
-            if DateTime::<Utc>::from_utc(
+            let rolled_over = DateTime::<Utc>::from_utc(
                 NaiveDateTime::from_timestamp((event.event_time / 1000) as i64, 0),
                 Utc,
-            ) > tmr_dt
-            {
+            ) > tmr_dt;
+
+            if rolled_over {
                 tmr_dt = Utc::today().and_hms(23, 59, 59);
-                let file_name = format!("{}-{}-{:?}.csv", symbol, "trades", Utc::today());
-                let file_path = std::path::Path::new(&file_name);
-                let local_wrt = csv::Writer::from_path(file_path).unwrap();
-                web_socket_handler = WebSocketHandler::new(local_wrt);
+                if let Err(error) = web_socket_handler.flush_to_file() {
+                    warn!("{}", error);
+                };
+                let base_path = format!("{}-{}-{:?}", symbol, "trades", Utc::today());
+                web_socket_handler = WebSocketHandler::new(build_sink(&data, &base_path));
             }
-    
+
             if let Err(error) = web_socket_handler.write_trades_to_file(&event) {
                 warn!("{}", error);
             };
+
+            for (builder, handler) in candle_builders.iter_mut().zip(candle_handlers.iter_mut()) {
+                if rolled_over {
+                    if let Err(error) = handler.flush_to_file() {
+                        warn!("{}", error);
+                    };
+                    let base_path = format!("{}-candle{}-{:?}", symbol, builder.interval, Utc::today());
+                    *handler = WebSocketHandler::new(build_sink(&data, &base_path));
+                }
+
+                if let Some(candle) = builder.on_trade(&event) {
+                    if let Err(error) = handler.write_candle_to_file(&candle) {
+                        warn!("{}", error);
+                    };
+                }
+            }
         }
     });
-    
+
     while let Err(e) = web_socket.event_loop(&keep_running).await {
         warn!("trades web_socket event_loop Error: {}, starting reconnect...", e);
     
@@ -353,31 +1296,53 @@ async fn main() {
     let c: Config = serde_json::from_reader(file).expect("file shoud be proper json");
 
     let mut tasks = Vec::new();
-    for symbol in c.data.symbol.iter() {
-        for ch in c.data.channels.iter() {
-            match ch.as_str() {
-                "depth@100ms" => {
-                    let symbol = symbol.clone();
-                    let task = actix_rt::spawn(async move {
-                        run_depth(symbol).await
-                    });
-                    tasks.push(task);
-                }
-                "aggTrade" => {
-                    let symbol = symbol.clone();
-                    let task = actix_rt::spawn(async move {
-                        run_trades(symbol).await
-                    });
-                    tasks.push(task);
-                }
-                _ => {
-                    warn!("Error: channel type not support!")
+
+    if c.data.combined {
+        // One reconnect-managed websocket for every (symbol, channel) pair,
+        // instead of a connection per pair.
+        let data = c.data.clone();
+        let task = actix_rt::spawn(async move { run_combined(data).await });
+        tasks.push(task);
+    } else {
+        for symbol in c.data.symbol.iter() {
+            let candle_resolutions: Vec<(String, u64)> = c
+                .data
+                .channels
+                .iter()
+                .filter_map(|ch| parse_candle_channel(ch))
+                .collect();
+
+            for ch in c.data.channels.iter() {
+                match ch.as_str() {
+                    "depth@100ms" => {
+                        let symbol = symbol.clone();
+                        let data = c.data.clone();
+                        let task = actix_rt::spawn(async move {
+                            run_depth(symbol, data).await
+                        });
+                        tasks.push(task);
+                    }
+                    "aggTrade" => {
+                        let symbol = symbol.clone();
+                        let candle_resolutions = candle_resolutions.clone();
+                        let data = c.data.clone();
+                        let task = actix_rt::spawn(async move {
+                            run_trades(symbol, candle_resolutions, data).await
+                        });
+                        tasks.push(task);
+                    }
+                    ch if ch.starts_with("candle@") => {
+                        // resolution is folded into the "aggTrade" task above
+                    }
+                    _ => {
+                        warn!("Error: channel type not support!")
+                    }
                 }
             }
         }
     }
 
     for task in tasks {
-        task.await.unwrap();  
+        task.await.unwrap();
     }
 }
\ No newline at end of file
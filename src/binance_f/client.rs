@@ -0,0 +1,132 @@
+use indexmap::IndexMap;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, USER_AGENT};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+use crate::binance_f::util::build_signed_request;
+use crate::errors::{BinanceContentError, Error, Result};
+
+#[derive(Clone)]
+pub struct Client {
+    api_key: String,
+    api_secret: String,
+    inner: reqwest::Client,
+    host: String,
+}
+
+impl Client {
+    pub fn new(api_key: Option<String>, api_secret: Option<String>, host: String) -> Self {
+        let builder: reqwest::ClientBuilder = reqwest::ClientBuilder::new();
+        let builder = builder.timeout(Duration::from_secs(2));
+        Client {
+            api_key: api_key.unwrap_or_else(|| "".into()),
+            api_secret: api_secret.unwrap_or_else(|| "".into()),
+            inner: builder.build().unwrap(),
+            host,
+        }
+    }
+
+    pub async fn get_signed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: IndexMap<String, String>,
+    ) -> Result<T> {
+        let url = self.sign_request(endpoint, params)?;
+        let response = self
+            .inner
+            .clone()
+            .get(url.as_str())
+            .headers(self.build_headers(true)?)
+            .send()
+            .await?;
+
+        self.handler(response).await
+    }
+
+    pub async fn post_signed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: IndexMap<String, String>,
+    ) -> Result<T> {
+        let url = self.sign_request(endpoint, params)?;
+        let response = self
+            .inner
+            .clone()
+            .post(url.as_str())
+            .headers(self.build_headers(true)?)
+            .send()
+            .await?;
+
+        self.handler(response).await
+    }
+
+    pub async fn put_signed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: IndexMap<String, String>,
+    ) -> Result<T> {
+        let url = self.sign_request(endpoint, params)?;
+        let response = self
+            .inner
+            .clone()
+            .put(url.as_str())
+            .headers(self.build_headers(true)?)
+            .send()
+            .await?;
+
+        self.handler(response).await
+    }
+
+    pub async fn delete_signed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: IndexMap<String, String>,
+    ) -> Result<T> {
+        let url = self.sign_request(endpoint, params)?;
+        let response = self
+            .inner
+            .clone()
+            .delete(url.as_str())
+            .headers(self.build_headers(true)?)
+            .send()
+            .await?;
+
+        self.handler(response).await
+    }
+
+    fn sign_request(&self, endpoint: &str, params: IndexMap<String, String>) -> Result<String> {
+        let request_body = build_signed_request(params, None, &self.api_secret)?;
+
+        Ok(format!("{}{}?{}", self.host, endpoint, request_body))
+    }
+
+    fn build_headers(&self, content_type: bool) -> Result<HeaderMap> {
+        let mut custom_headers = HeaderMap::new();
+        custom_headers.insert(USER_AGENT, HeaderValue::from_static("exrs"));
+        if content_type {
+            custom_headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            );
+        }
+        custom_headers.insert(
+            HeaderName::from_static("x-mbx-apikey"),
+            HeaderValue::from_str(self.api_key.as_str())?,
+        );
+
+        Ok(custom_headers)
+    }
+
+    async fn handler<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json::<T>().await?);
+        }
+
+        match response.json::<BinanceContentError>().await {
+            Ok(error) => Err(Error::BinanceError(error)),
+            Err(_) => Err(Error::UnexpectedStatus(status)),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Body of a Binance error response, e.g. `{"code":-1121,"msg":"Invalid symbol."}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceContentError {
+    pub code: i32,
+    pub msg: String,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("binance error: {0:?}")]
+    BinanceError(BinanceContentError),
+    #[error("unexpected response status: {0}")]
+    UnexpectedStatus(reqwest::StatusCode),
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    #[error(transparent)]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error(transparent)]
+    SystemTime(#[from] std::time::SystemTimeError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
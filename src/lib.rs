@@ -0,0 +1,3 @@
+pub mod binance_f;
+pub mod errors;
+pub mod huobi;